@@ -3,6 +3,7 @@ use float_ord::FloatOrd;
 use geo::{
     line_intersection::line_intersection, line_intersection::LineIntersection, Coordinate, Line,
 };
+use std::cmp::Reverse;
 use std::collections::{BinaryHeap, VecDeque};
 
 #[derive(Debug, Clone, Copy)]
@@ -58,12 +59,39 @@ fn create_mountain(birth: f32, death: f32, index: usize) -> PersistenceMountain
     };
 }
 
-fn generate_mountains(bd_pairs: Vec<BirthDeath>) -> Vec<PersistenceMountain> {
+/// Like `create_mountain`, but a non-finite `death` (an essential class with
+/// infinite persistence) is clamped to `cutoff` instead of being unrepresentable,
+/// so it still produces a proper rising/middle/falling tent.
+fn create_mountain_with_cutoff(
+    birth: f32,
+    death: f32,
+    index: usize,
+    cutoff: Option<f32>,
+) -> PersistenceMountain {
+    let clamped_death = if death.is_finite() {
+        death
+    } else {
+        cutoff.expect("generate_mountains_from only keeps non-finite deaths when a cutoff is set")
+    };
+    return create_mountain(birth, clamped_death, index);
+}
+
+fn generate_mountains(bd_pairs: Vec<BirthDeath>, cutoff: Option<f32>) -> Vec<PersistenceMountain> {
+    return generate_mountains_from(bd_pairs, 0, cutoff);
+}
+
+fn generate_mountains_from(
+    bd_pairs: Vec<BirthDeath>,
+    start_id: usize,
+    cutoff: Option<f32>,
+) -> Vec<PersistenceMountain> {
     return bd_pairs
         .into_iter()
-        .filter(|BirthDeath { birth, death }| death.is_finite() && birth.is_finite())
+        .filter(|BirthDeath { birth, death }| birth.is_finite() && (death.is_finite() || cutoff.is_some()))
         .enumerate()
-        .map(|(i, BirthDeath { birth, death })| create_mountain(birth, death, i))
+        .map(|(i, BirthDeath { birth, death })| {
+            create_mountain_with_cutoff(birth, death, start_id + i, cutoff)
+        })
         .collect::<Vec<_>>();
 }
 
@@ -126,21 +154,47 @@ fn create_line_segment(mountain: PersistenceMountain) -> Line<f32> {
     };
 }
 
-fn intersects_with_neighbor(m1: PersistenceMountain, m2: PersistenceMountain) -> Option<PointOrd> {
+fn intersects_with_neighbor(m1: PersistenceMountain, m2: PersistenceMountain) -> Vec<PointOrd> {
     if m1.slope_rising == m2.slope_rising {
-        return None;
+        return Vec::new();
     }
     return match line_intersection(create_line_segment(m1), create_line_segment(m2)) {
+        // A normal interior crossing, or one that merely touches at a
+        // shared endpoint (tied birth/death): either way the two mountains
+        // need to swap order, so both are emitted as a single Intersection
+        // event.
         Some(LineIntersection::SinglePoint {
             intersection: Coordinate { x, y },
-            is_proper: true,
-        }) => Some(PointOrd {
+            is_proper: _,
+        }) => vec![PointOrd {
             x: FloatOrd(x),
             y: FloatOrd(y),
-        }),
-        // Ignore all colinnear, not proper and no intersection results these will be resolved on
-        // slope change or do not matter
-        _ => None,
+        }],
+        // Overlapping (collinear) segments swap order twice: once where the
+        // overlap begins and once where it ends. `create_mountain` always
+        // gives rising segments slope +1 and falling segments slope -1, so
+        // this never actually fires for mountains built that way (the
+        // shared-endpoint case is already covered above); kept for
+        // robustness against any other segment source.
+        Some(LineIntersection::Collinear {
+            intersection: Line { start, end },
+        }) => {
+            let a = PointOrd {
+                x: FloatOrd(start.x),
+                y: FloatOrd(start.y),
+            };
+            let b = PointOrd {
+                x: FloatOrd(end.x),
+                y: FloatOrd(end.y),
+            };
+            let (entry, exit) = if a <= b { (a, b) } else { (b, a) };
+            if entry == exit {
+                vec![entry]
+            } else {
+                vec![entry, exit]
+            }
+        }
+        None => Vec::new(),
     };
 }
 
@@ -161,87 +215,697 @@ fn handle_intersection(
     m1: PersistenceMountain,
     mountains: &mut Vec<PersistenceMountain>,
     offset: i8,
-) -> Option<Event> {
+) -> Vec<Event> {
     let position = m1.position.expect("Mountain with event is dead");
     // = status.get(mountains[event.parent_mountain_id].position + 1)
     let neighbor_index = match offset {
-        1 => position + 1,
-        -1 => position - 1,
+        1 => Some(position + 1),
+        -1 => position.checked_sub(1),
         _ => unreachable!("Can only look at neighbors in status"),
     };
 
-    if let Some(neighbor) = status.get(neighbor_index) {
-        if let Some(intersection) = intersects_with_neighbor(m1, mountains[*neighbor]) {
-            return Some(Event {
+    if let Some(neighbor) = neighbor_index.and_then(|index| status.get(index)) {
+        let m2 = mountains[*neighbor];
+        // Order the pair by id regardless of which side found the other, so
+        // the same crossing always produces the same Event fields no matter
+        // which mountain's check discovers it.
+        let (id, id2) = (m1.id.min(m2.id), m1.id.max(m2.id));
+        return intersects_with_neighbor(m1, m2)
+            .into_iter()
+            .map(|intersection| Event {
                 value: intersection,
                 event_type: EventType::Intersection,
-                parent_mountain_id: m1.id,
-                parent_mountain2_id: Some(*neighbor),
-            });
+                parent_mountain_id: id,
+                parent_mountain2_id: Some(id2),
+            })
+            .collect();
+    }
+    return Vec::new();
+}
+
+fn reindex_mountain(mountain: PersistenceMountain, offset: usize) -> PersistenceMountain {
+    return PersistenceMountain {
+        id: mountain.id + offset,
+        ..mountain
+    };
+}
+
+fn reindex_event(event: Event, offset: usize) -> Event {
+    return Event {
+        parent_mountain_id: event.parent_mountain_id + offset,
+        parent_mountain2_id: event.parent_mountain2_id.map(|id| id + offset),
+        ..event
+    };
+}
+
+/// Merges two status stacks that are each already ordered top-to-bottom by
+/// height (index 0 is the highest mountain, as in the sweep's `status`) into
+/// one stack with that same invariant, comparing mountains by
+/// the height the sweep last left them at. Doesn't reassign `position`; the
+/// caller does that once against the merged result.
+fn merge_status(
+    left: VecDeque<usize>,
+    right: VecDeque<usize>,
+    mountains: &[PersistenceMountain],
+) -> VecDeque<usize> {
+    let height = |id: usize| current_segment_start(mountains[id]).1;
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    let mut merged = VecDeque::new();
+    loop {
+        match (left.peek(), right.peek()) {
+            (Some(&l), Some(&r)) if height(l) >= height(r) => merged.push_back(left.next().unwrap()),
+            (Some(_), Some(_)) => merged.push_back(right.next().unwrap()),
+            (Some(_), None) => merged.push_back(left.next().unwrap()),
+            (None, Some(_)) => merged.push_back(right.next().unwrap()),
+            (None, None) => break,
         }
     }
-    return None;
+    return merged;
 }
 
-pub fn generate(bd_pairs: Vec<BirthDeath>, k: usize) -> Vec<Vec<PointOrd>> {
-    let landscapes = &mut Vec::with_capacity(k as usize);
-    let mountains = &mut generate_mountains(bd_pairs);
-    let events = &mut BinaryHeap::from(generate_initial_events(mountains.to_vec()));
-    let status = &mut VecDeque::new();
-
-    while let Some(event) = events.pop() {
-        match event.event_type {
-            EventType::Birth => {
-                // Add to status structure
-                status.push_back(event.parent_mountain_id);
-                let position = status.len() - 1;
-                mountains[event.parent_mountain_id].position = Some(position);
-                // Add to output if needed
-                log_to_landscape(mountains[event.parent_mountain_id], event, landscapes, k);
-                // Check for intersections
-                if let Some(new_event) =
-                    handle_intersection(status, mountains[event.parent_mountain_id], mountains, -1)
-                {
-                    events.push(new_event);
-                }
+/// The minimal state needed to resume a landscape sweep: the mountains that
+/// are still alive, their current slots in `status`, the events not yet
+/// popped from the heap, and the output accumulated so far. Lets a caller
+/// extend a landscape with new birth-death pairs without reprocessing the
+/// pairs it already swept.
+pub struct LandscapeState {
+    mountains: Vec<PersistenceMountain>,
+    // `Event`'s derived `Ord` is ascending by `value`, but `BinaryHeap` is a
+    // max-heap; wrapping in `Reverse` makes `pop()` return the
+    // smallest-x (i.e. next in sweep order) event first.
+    events: BinaryHeap<Reverse<Event>>,
+    status: VecDeque<usize>,
+    landscapes: Vec<Vec<PointOrd>>,
+    k: usize,
+    // The maximum filtration value essential (infinite-death) pairs are
+    // clamped to; `None` means they're dropped, matching `generate`.
+    cutoff: Option<f32>,
+    // The last crossing we acted on (or found stale); an identical
+    // Intersection event popped right after it is skipped rather than
+    // processed twice.
+    last_intersection: Option<(usize, usize, PointOrd)>,
+}
+
+impl LandscapeState {
+    /// Builds the initial mountains and events but doesn't sweep them yet:
+    /// the sweep only actually runs once `output` is called, so `append`ing
+    /// or `fuse`ing more pairs in first still finds crossings between this
+    /// batch and the new one instead of meeting an already-finalized, empty
+    /// `status`.
+    pub fn new(bd_pairs: Vec<BirthDeath>, k: usize) -> LandscapeState {
+        return LandscapeState::new_with_cutoff(bd_pairs, k, None);
+    }
+
+    pub fn new_with_cutoff(bd_pairs: Vec<BirthDeath>, k: usize, cutoff: Option<f32>) -> LandscapeState {
+        let mountains = generate_mountains(bd_pairs, cutoff);
+        let events = generate_initial_events(mountains.to_vec())
+            .into_iter()
+            .map(Reverse)
+            .collect();
+        return LandscapeState {
+            landscapes: vec![Vec::new(); k],
+            mountains,
+            events,
+            status: VecDeque::new(),
+            k,
+            cutoff,
+            last_intersection: None,
+        };
+    }
+
+    /// Drains whatever events are still queued and returns the landscape
+    /// computed so far. Resuming an already-drained heap is a no-op, so this
+    /// is safe to call more than once, or interleaved with `append`/`fuse`:
+    /// each call only processes what's been added since the last one.
+    pub fn output(&mut self) -> Vec<Vec<PointOrd>> {
+        self.run();
+        return self.landscapes.to_vec();
+    }
+
+    fn push_event(&mut self, event: Event) {
+        self.events.push(Reverse(event));
+    }
+
+    /// Turns `new_pairs` into mountains and events, merges them into the
+    /// still-running heap (their birth/middle/death events will trigger the
+    /// usual neighbor checks against whatever is left in `status`), and
+    /// resumes the sweep. Essential pairs are clamped using the same cutoff
+    /// the state was built with.
+    pub fn append(&mut self, new_pairs: Vec<BirthDeath>) {
+        let offset = self.mountains.len();
+        let new_mountains = generate_mountains_from(new_pairs, offset, self.cutoff);
+        for event in generate_initial_events(new_mountains.to_vec()) {
+            self.push_event(event);
+        }
+        self.mountains.extend(new_mountains);
+        self.run();
+    }
+
+    /// Combines two independently-built states whose x-ranges may overlap.
+    /// `other`'s mountains, events and status entries are reindexed so they
+    /// don't collide with `self`'s ids. The two status stacks are merged by
+    /// height rather than concatenated, and every still-alive mountain's
+    /// `position` is reassigned to its real slot in the merged stack, before
+    /// the merged sweep resumes so any crossings at the seam between the two
+    /// batches are still found.
+    pub fn fuse(mut self, other: LandscapeState) -> LandscapeState {
+        let offset = self.mountains.len();
+
+        self.mountains
+            .extend(other.mountains.into_iter().map(|m| reindex_mountain(m, offset)));
+        for Reverse(event) in other.events.into_iter() {
+            self.push_event(reindex_event(event, offset));
+        }
+
+        let other_status: VecDeque<usize> = other.status.into_iter().map(|id| id + offset).collect();
+        self.status = merge_status(self.status, other_status, &self.mountains);
+        for (index, &id) in self.status.iter().enumerate() {
+            self.mountains[id].position = Some(index);
+        }
+
+        for (depth, layer) in other.landscapes.into_iter().enumerate() {
+            match self.landscapes.get_mut(depth) {
+                Some(existing) => existing.extend(layer),
+                None => self.landscapes.push(layer),
             }
-            EventType::Middle => {
-                // Update status structures
-                mountains[event.parent_mountain_id].slope_rising = false;
-                // Add to ouput if needed
-                log_to_landscape(mountains[event.parent_mountain_id], event, landscapes, k);
-                // Check for intersections
-                if let Some(new_event) =
-                    handle_intersection(status, mountains[event.parent_mountain_id], mountains, 1)
-                {
-                    events.push(new_event);
+        }
+        self.k = self.k.max(other.k);
+        self.cutoff = match (self.cutoff, other.cutoff) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (cutoff, None) | (None, cutoff) => cutoff,
+        };
+
+        self.run();
+        return self;
+    }
+
+    fn run(&mut self) {
+        while let Some(Reverse(event)) = self.events.pop() {
+            match event.event_type {
+                EventType::Birth => {
+                    // Add to status structure
+                    self.status.push_back(event.parent_mountain_id);
+                    let position = self.status.len() - 1;
+                    self.mountains[event.parent_mountain_id].position = Some(position);
+                    // Add to output if needed
+                    log_to_landscape(
+                        self.mountains[event.parent_mountain_id],
+                        event,
+                        &mut self.landscapes,
+                        self.k,
+                    );
+                    // Check for intersections
+                    for new_event in handle_intersection(
+                        &mut self.status,
+                        self.mountains[event.parent_mountain_id],
+                        &mut self.mountains,
+                        -1,
+                    ) {
+                        self.push_event(new_event);
+                    }
                 }
-            }
-            EventType::Death => {
-                // Add to ouput if needed
-                log_to_landscape(mountains[event.parent_mountain_id], event, landscapes, k);
-                // remove and disable
-                status.pop_back();
-                mountains[event.parent_mountain_id].position = None;
-            }
-            EventType::Intersection => {
-                // Add to ouput if needed
-                log_to_landscape(mountains[event.parent_mountain_id], event, landscapes, k);
-                log_to_landscape(
-                    mountains[event
+                EventType::Middle => {
+                    // Update status structures
+                    self.mountains[event.parent_mountain_id].slope_rising = false;
+                    // Add to ouput if needed
+                    log_to_landscape(
+                        self.mountains[event.parent_mountain_id],
+                        event,
+                        &mut self.landscapes,
+                        self.k,
+                    );
+                    // Check for intersections
+                    for new_event in handle_intersection(
+                        &mut self.status,
+                        self.mountains[event.parent_mountain_id],
+                        &mut self.mountains,
+                        1,
+                    ) {
+                        self.push_event(new_event);
+                    }
+                }
+                EventType::Death => {
+                    // Add to ouput if needed
+                    log_to_landscape(
+                        self.mountains[event.parent_mountain_id],
+                        event,
+                        &mut self.landscapes,
+                        self.k,
+                    );
+                    // remove and disable
+                    self.status.pop_back();
+                    self.mountains[event.parent_mountain_id].position = None;
+                }
+                EventType::Intersection => {
+                    let id = event.parent_mountain_id;
+                    let id2 = event
                         .parent_mountain2_id
-                        .expect("Intersection event with no second mountain")],
-                    event,
-                    landscapes,
-                    k,
-                );
-                // Ensure the intersection event is setup properly
-                assert!(mountains[event.parent_mountain_id].slope_rising == true);
-                // Swap
-                // Check for intersections
+                        .expect("Intersection event with no second mountain");
+                    let key = (id.min(id2), id.max(id2), event.value);
+                    if self.last_intersection == Some(key) {
+                        continue;
+                    }
+
+                    // A queued crossing can go stale: if both mountains hit
+                    // their own Middle event at this same point first (a
+                    // tied apex), they've already both turned falling and
+                    // there's nothing left to swap.
+                    let (rising_id, falling_id) =
+                        match (self.mountains[id].slope_rising, self.mountains[id2].slope_rising) {
+                            (true, false) => (id, id2),
+                            (false, true) => (id2, id),
+                            _ => {
+                                self.last_intersection = Some(key);
+                                continue;
+                            }
+                        };
+
+                    // Add to ouput if needed
+                    log_to_landscape(self.mountains[id], event, &mut self.landscapes, self.k);
+                    log_to_landscape(self.mountains[id2], event, &mut self.landscapes, self.k);
+
+                    let rising_position = self.mountains[rising_id]
+                        .position
+                        .expect("Mountain with event is dead");
+                    let falling_position = self.mountains[falling_id]
+                        .position
+                        .expect("Mountain with event is dead");
+                    assert!(
+                        (rising_position as isize - falling_position as isize).abs() == 1,
+                        "only vertically adjacent mountains can intersect"
+                    );
+
+                    // Swap the intersecting pair's slots and point each
+                    // mountain at its new position.
+                    self.status.swap(rising_position, falling_position);
+                    self.mountains[rising_id].position = Some(falling_position);
+                    self.mountains[falling_id].position = Some(rising_position);
+
+                    // Only the two new outer adjacencies created by the swap
+                    // can produce a fresh crossing: the rising mountain,
+                    // now the upper of the two, against the slot above it
+                    // (index - 1), and the falling mountain against the slot
+                    // below it (index + 1).
+                    for new_event in
+                        handle_intersection(&mut self.status, self.mountains[rising_id], &mut self.mountains, -1)
+                    {
+                        self.push_event(new_event);
+                    }
+                    for new_event in
+                        handle_intersection(&mut self.status, self.mountains[falling_id], &mut self.mountains, 1)
+                    {
+                        self.push_event(new_event);
+                    }
+
+                    self.last_intersection = Some(key);
+                }
             }
         }
     }
+}
+
+pub fn generate(bd_pairs: Vec<BirthDeath>, k: usize) -> Vec<Vec<PointOrd>> {
+    return LandscapeState::new(bd_pairs, k).output();
+}
+
+/// Like `generate`, but a pair with infinite death (an essential class) is
+/// kept and clamped to `cutoff` instead of being dropped. `cutoff` of `None`
+/// preserves `generate`'s behavior of filtering those pairs out.
+pub fn generate_with_cutoff(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    cutoff: Option<f32>,
+) -> Vec<Vec<PointOrd>> {
+    return LandscapeState::new_with_cutoff(bd_pairs, k, cutoff).output();
+}
+
+/// Evaluates the piecewise-linear function described by `layer`'s
+/// breakpoints at `x`. Landscape layers are zero outside their support, so
+/// `x` outside `layer`'s range (or an empty layer) evaluates to `0.0`.
+fn eval_layer(layer: &[PointOrd], x: f32) -> f32 {
+    if layer.is_empty() || x < layer[0].x.0 || x > layer[layer.len() - 1].x.0 {
+        return 0.0;
+    }
+    for window in layer.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        if x >= start.x.0 && x <= end.x.0 {
+            if end.x.0 == start.x.0 {
+                return start.y.0;
+            }
+            let t = (x - start.x.0) / (end.x.0 - start.x.0);
+            return start.y.0 + t * (end.y.0 - start.y.0);
+        }
+    }
+    return 0.0;
+}
+
+/// Pointwise-combines two (possibly absent) layers over the union of their
+/// x-breakpoints. A missing layer is treated as the zero function.
+fn combine_layer(
+    a: Option<&Vec<PointOrd>>,
+    b: Option<&Vec<PointOrd>>,
+    f: impl Fn(f32, f32) -> f32,
+) -> Vec<PointOrd> {
+    let empty = Vec::new();
+    let a = a.unwrap_or(&empty);
+    let b = b.unwrap_or(&empty);
+
+    let mut xs: Vec<FloatOrd<f32>> = a.iter().chain(b.iter()).map(|point| point.x).collect();
+    xs.sort();
+    xs.dedup();
+
+    return xs
+        .into_iter()
+        .map(|x| PointOrd {
+            x,
+            y: FloatOrd(f(eval_layer(a, x.0), eval_layer(b, x.0))),
+        })
+        .collect();
+}
+
+/// The definite integral of `|y(x)|^p` over a linear piece from `(x0, y0)`
+/// to `(x1, y1)`, in closed form. Splits at the zero-crossing first so each
+/// half can be integrated as a signed power of a linear function.
+fn segment_lp_integral(x0: f32, y0: f32, x1: f32, y1: f32, p: f32) -> f32 {
+    if x1 <= x0 {
+        return 0.0;
+    }
+    if y0 * y1 < 0.0 {
+        let t = y0 / (y0 - y1);
+        let x_mid = x0 + t * (x1 - x0);
+        return segment_lp_integral(x0, y0, x_mid, 0.0, p)
+            + segment_lp_integral(x_mid, 0.0, x1, y1, p);
+    }
+
+    let dx = x1 - x0;
+    if (y1 - y0).abs() < f32::EPSILON {
+        return y0.abs().powf(p) * dx;
+    }
+
+    // No sign change over [x0, x1], so |y| == sign * y throughout.
+    let sign = if y0 + y1 >= 0.0 { 1.0 } else { -1.0 };
+    let slope = sign * (y1 - y0) / dx;
+    let g0 = sign * y0;
+    let g1 = sign * y1;
+    return (g1.powf(p + 1.0) - g0.powf(p + 1.0)) / ((p + 1.0) * slope);
+}
+
+/// A persistence landscape as a vector in the Banach space of bounded
+/// sequences of piecewise-linear functions, so that landscapes produced from
+/// different samples can be averaged and compared. Wraps the same
+/// `Vec<Vec<PointOrd>>` layers that [`generate`] produces.
+#[derive(Debug, Clone)]
+pub struct Landscape {
+    layers: Vec<Vec<PointOrd>>,
+}
+
+impl Landscape {
+    pub fn new(layers: Vec<Vec<PointOrd>>) -> Landscape {
+        return Landscape { layers };
+    }
+
+    pub fn layers(&self) -> &Vec<Vec<PointOrd>> {
+        return &self.layers;
+    }
+
+    fn combine(&self, other: &Landscape, f: impl Fn(f32, f32) -> f32 + Copy) -> Landscape {
+        let depth = self.layers.len().max(other.layers.len());
+        let layers = (0..depth)
+            .map(|k| combine_layer(self.layers.get(k), other.layers.get(k), f))
+            .collect();
+        return Landscape { layers };
+    }
+
+    pub fn add(&self, other: &Landscape) -> Landscape {
+        return self.combine(other, |a, b| a + b);
+    }
+
+    pub fn sub(&self, other: &Landscape) -> Landscape {
+        return self.combine(other, |a, b| a - b);
+    }
+
+    pub fn scale(&self, factor: f32) -> Landscape {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|point| PointOrd {
+                        x: point.x,
+                        y: FloatOrd(point.y.0 * factor),
+                    })
+                    .collect()
+            })
+            .collect();
+        return Landscape { layers };
+    }
+
+    pub fn mean(landscapes: &[Landscape]) -> Landscape {
+        let (first, rest) = landscapes
+            .split_first()
+            .expect("mean requires at least one landscape");
+        let mut sum = first.clone();
+        for landscape in rest {
+            sum = sum.add(landscape);
+        }
+        return sum.scale(1.0 / landscapes.len() as f32);
+    }
+
+    pub fn lp_norm(&self, p: f32) -> f32 {
+        let sum: f32 = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.windows(2))
+            .map(|window| segment_lp_integral(window[0].x.0, window[0].y.0, window[1].x.0, window[1].y.0, p))
+            .sum();
+        return sum.powf(1.0 / p);
+    }
+
+    pub fn lp_distance(&self, other: &Landscape, p: f32) -> f32 {
+        return self.sub(other).lp_norm(p);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> PointOrd {
+        return PointOrd {
+            x: FloatOrd(x),
+            y: FloatOrd(y),
+        };
+    }
+
+    #[test]
+    fn identical_pairs_do_not_swap() {
+        // Two mountains built from the exact same birth/death pair sit
+        // exactly on top of each other; their apexes touch but neither ever
+        // outranks the other, so no real crossing occurs.
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 0.0, death: 4.0 },
+        ];
+        let layers = generate(pairs, 2);
+        assert_eq!(
+            layers[0],
+            vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]
+        );
+        assert_eq!(layers[1], layers[0]);
+    }
+
+    #[test]
+    fn repeated_pairs_dedupe_across_every_combination() {
+        // Three identical mountains all touch at the same apex; each pair's
+        // queued crossing must be recognized as stale, not just the first
+        // one processed.
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 0.0, death: 4.0 },
+        ];
+        let layers = generate(pairs, 3);
+        for layer in &layers {
+            assert_eq!(
+                *layer,
+                vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]
+            );
+        }
+    }
+
+    #[test]
+    fn nested_mountain_stays_below_without_crossing() {
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 1.0, death: 3.0 },
+        ];
+        let layers = generate(pairs, 2);
+        assert_eq!(
+            layers[0],
+            vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]
+        );
+        assert_eq!(
+            layers[1],
+            vec![point(1.0, 0.0), point(2.0, 1.0), point(3.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn touching_apex_does_not_force_a_swap() {
+        // The second mountain's rise meets the first's fall exactly at the
+        // second mountain's own apex: an `is_proper: false` touch rather
+        // than an interior crossing, so the two never actually change
+        // order.
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 2.0, death: 4.0 },
+        ];
+        let layers = generate(pairs, 2);
+        assert_eq!(
+            layers[0],
+            vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]
+        );
+        assert_eq!(
+            layers[1],
+            vec![point(2.0, 0.0), point(3.0, 1.0), point(4.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn crossing_mountains_swap_order() {
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 1.0, death: 5.0 },
+        ];
+        let layers = generate(pairs, 2);
+        assert_eq!(
+            layers[0],
+            vec![
+                point(0.0, 0.0),
+                point(2.0, 2.0),
+                point(2.5, 1.5),
+                point(3.0, 2.0),
+                point(5.0, 0.0),
+            ]
+        );
+        assert_eq!(
+            layers[1],
+            vec![point(1.0, 0.0), point(2.5, 1.5), point(4.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn merge_status_interleaves_by_height() {
+        // `left` (ids 1, 0) and `right` (ids 2, 3) are each already ordered
+        // top-to-bottom on their own, but 2 sits between 0 and 1 in height.
+        let mut mountains = vec![
+            create_mountain(0.0, 4.0, 0),
+            create_mountain(0.0, 10.0, 1),
+            create_mountain(0.0, 2.0, 2),
+            create_mountain(0.0, 2.0, 3),
+        ];
+        mountains[1].slope_rising = false; // height becomes its middle.y, 5.0
+        mountains[2].slope_rising = false; // height becomes its middle.y, 1.0
+
+        let left: VecDeque<usize> = vec![1, 0].into_iter().collect();
+        let right: VecDeque<usize> = vec![2, 3].into_iter().collect();
+        let merged = merge_status(left, right, &mountains);
+
+        assert_eq!(merged, vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn fuse_finds_crossings_between_batches() {
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 1.0, death: 5.0 },
+        ];
+        let expected = generate(pairs, 2);
+
+        let a = LandscapeState::new(vec![BirthDeath { birth: 0.0, death: 4.0 }], 2);
+        let b = LandscapeState::new(vec![BirthDeath { birth: 1.0, death: 5.0 }], 2);
+        let mut fused = a.fuse(b);
 
-    return landscapes.to_vec();
+        assert_eq!(fused.output(), expected);
+    }
+
+    #[test]
+    fn append_finds_crossings_with_already_built_mountains() {
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: 4.0 },
+            BirthDeath { birth: 1.0, death: 5.0 },
+        ];
+        let expected = generate(pairs, 2);
+
+        let mut state = LandscapeState::new(vec![BirthDeath { birth: 0.0, death: 4.0 }], 2);
+        state.append(vec![BirthDeath { birth: 1.0, death: 5.0 }]);
+
+        assert_eq!(state.output(), expected);
+    }
+
+    #[test]
+    fn add_combines_layers_over_unioned_breakpoints() {
+        let a = Landscape::new(vec![vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]]);
+        let b = Landscape::new(vec![vec![point(1.0, 0.0), point(3.0, 2.0), point(5.0, 0.0)]]);
+        let sum = a.add(&b);
+        assert_eq!(
+            sum.layers()[0],
+            vec![
+                point(0.0, 0.0),
+                point(1.0, 1.0),
+                point(2.0, 3.0),
+                point(3.0, 3.0),
+                point(4.0, 1.0),
+                point(5.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn mean_averages_two_landscapes() {
+        let a = Landscape::new(vec![vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]]);
+        let b = Landscape::new(vec![vec![point(0.0, 0.0), point(2.0, 4.0), point(4.0, 0.0)]]);
+        let mean = Landscape::mean(&[a, b]);
+        assert_eq!(
+            mean.layers()[0],
+            vec![point(0.0, 0.0), point(2.0, 3.0), point(4.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn lp_norm_matches_hand_computed_triangle_area() {
+        // The area under the tent (0,0)-(2,2)-(4,0) is a triangle of base 4
+        // and height 2.
+        let landscape = Landscape::new(vec![vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]]);
+        assert_eq!(landscape.lp_norm(1.0), 4.0);
+    }
+
+    #[test]
+    fn lp_distance_between_identical_landscapes_is_zero() {
+        let landscape = Landscape::new(vec![vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]]);
+        assert_eq!(landscape.lp_distance(&landscape, 1.0), 0.0);
+    }
+
+    #[test]
+    fn cutoff_clamps_essential_pairs_to_the_given_bound() {
+        let pairs = vec![BirthDeath { birth: 0.0, death: f32::INFINITY }];
+        let layers = generate_with_cutoff(pairs, 1, Some(4.0));
+        assert_eq!(
+            layers[0],
+            vec![point(0.0, 0.0), point(2.0, 2.0), point(4.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn cutoff_none_still_filters_essential_pairs_like_generate() {
+        let pairs = vec![
+            BirthDeath { birth: 0.0, death: f32::INFINITY },
+            BirthDeath { birth: 1.0, death: 3.0 },
+        ];
+        let expected = generate(pairs.clone(), 2);
+        let actual = generate_with_cutoff(pairs, 2, None);
+        assert_eq!(actual, expected);
+    }
 }
\ No newline at end of file